@@ -1,13 +1,56 @@
 use anyhow::{bail, Error as AnyError, Result};
 use chrono::prelude::*;
+use rust_decimal::Decimal;
 use serde::de::{self, Deserializer, Unexpected};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
 use std::str::FromStr;
 
 pub trait InputRecord {
     fn get_date(&self) -> &String;
 
-    fn get_amount(&self) -> f64;
+    fn get_amount(&self) -> Decimal;
+
+    /// Candidate timestamp layouts for this source, tried in order by
+    /// [`parse_date`]. The first that matches wins.
+    fn date_formats() -> &'static [DateFormat]
+    where
+        Self: Sized;
+}
+
+/// A candidate timestamp layout for an [`InputRecord`].
+#[derive(Debug, Clone, Copy)]
+pub enum DateFormat {
+    /// A `strftime` pattern parsed as a naive (timezone-less) datetime.
+    Naive(&'static str),
+    /// A `strftime` pattern that carries a fixed UTC offset; the parsed value
+    /// is normalized to UTC before dropping the offset.
+    Offset(&'static str),
+    /// RFC 3339 / ISO-8601 with an offset, normalized to UTC.
+    Rfc3339,
+}
+
+/// Try each layout in `formats` against `input`, returning the first that
+/// parses. Offset-bearing layouts are normalized to UTC so every record lands
+/// on a common, comparable [`NaiveDateTime`].
+pub fn parse_date(input: &str, formats: &[DateFormat]) -> Result<NaiveDateTime> {
+    for format in formats {
+        let parsed = match format {
+            DateFormat::Naive(pattern) => NaiveDateTime::parse_from_str(input, pattern).ok(),
+            DateFormat::Offset(pattern) => {
+                DateTime::parse_from_str(input, pattern).ok().map(|dt| dt.naive_utc())
+            }
+            DateFormat::Rfc3339 => DateTime::parse_from_rfc3339(input).ok().map(|dt| dt.naive_utc()),
+        };
+
+        if let Some(date) = parsed {
+            return Ok(date);
+        }
+    }
+
+    bail!("Could not parse date `{}` with any known format", input)
 }
 
 #[derive(Debug, Deserialize)]
@@ -25,7 +68,7 @@ pub struct Subscan {
     pub extrinsic: String,
 
     #[serde(rename = "Value")]
-    pub amount: f64,
+    pub amount: Decimal,
 
     #[serde(rename = "Action")]
     pub action: String,
@@ -36,9 +79,13 @@ impl InputRecord for Subscan {
         &self.date
     }
 
-    fn get_amount(&self) -> f64 {
+    fn get_amount(&self) -> Decimal {
         self.amount
     }
+
+    fn date_formats() -> &'static [DateFormat] {
+        &[DateFormat::Naive("%Y-%m-%d %H:%M:%S")]
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -54,8 +101,8 @@ pub struct Kraken {
 
     pub aclass: String,
     pub asset: String,
-    pub amount: f64,
-    pub fee: f64,
+    pub amount: Decimal,
+    pub fee: Decimal,
 }
 
 impl InputRecord for Kraken {
@@ -63,9 +110,17 @@ impl InputRecord for Kraken {
         &self.date
     }
 
-    fn get_amount(&self) -> f64 {
+    fn get_amount(&self) -> Decimal {
         self.amount
     }
+
+    fn date_formats() -> &'static [DateFormat] {
+        &[
+            DateFormat::Naive("%Y-%m-%d %H:%M:%S"),
+            // Some Kraken exports use US-style 12-hour timestamps.
+            DateFormat::Naive("%m/%d/%Y, %I:%M:%S %p"),
+        ]
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -74,11 +129,11 @@ pub struct StakeTax {
     pub tx_type: String,
     #[serde(deserialize_with = "bool_from_string")]
     pub taxable: bool,
-    pub received_amount: Option<f64>,
+    pub received_amount: Option<Decimal>,
     pub received_currency: String,
-    pub sent_amount: Option<f64>,
+    pub sent_amount: Option<Decimal>,
     pub sent_currency: String,
-    pub fee: Option<f64>,
+    pub fee: Option<Decimal>,
     pub fee_currency: String,
     pub comment: String,
     #[serde(rename = "txid")]
@@ -93,11 +148,15 @@ impl InputRecord for StakeTax {
         &self.timestamp
     }
 
-    fn get_amount(&self) -> f64 {
-        match self.received_amount {
-            Some(amount) => amount,
-            None => 0f64,
-        }
+    fn get_amount(&self) -> Decimal {
+        self.received_amount.unwrap_or(Decimal::ZERO)
+    }
+
+    fn date_formats() -> &'static [DateFormat] {
+        &[
+            DateFormat::Naive("%Y-%m-%d %H:%M:%S"),
+            DateFormat::Rfc3339,
+        ]
     }
 }
 
@@ -112,6 +171,7 @@ pub enum InputFormat {
 pub enum OutputFormat {
     BitcoinTax,
     CoinTracking,
+    Ledger,
 }
 
 #[derive(Debug)]
@@ -138,6 +198,45 @@ impl FromStr for Quarter {
     }
 }
 
+/// How the `--summary` report groups rewards into subtotal buckets.
+#[derive(Debug, Clone, Copy)]
+pub enum Period {
+    Month,
+    Quarter,
+    HalfYear,
+}
+
+impl FromStr for Period {
+    type Err = AnyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match &s.to_lowercase()[..] {
+            "month" | "monthly" => Ok(Period::Month),
+            "quarter" | "quarterly" => Ok(Period::Quarter),
+            "half" | "half-year" | "halfyear" => Ok(Period::HalfYear),
+            _ => bail!("Invalid period! Use month, quarter or half-year."),
+        }
+    }
+}
+
+impl Period {
+    /// Label of the bucket `date` falls into, e.g. `2021-03`, `2021-Q1`,
+    /// `2021-H1`.
+    pub fn label(&self, date: NaiveDateTime) -> String {
+        match self {
+            Period::Month => date.format("%Y-%m").to_string(),
+            Period::Quarter => {
+                let quarter = (date.month() - 1) / 3 + 1;
+                format!("{}-Q{}", date.year(), quarter)
+            }
+            Period::HalfYear => {
+                let half = if date.month() <= 6 { 1 } else { 2 };
+                format!("{}-H{}", date.year(), half)
+            }
+        }
+    }
+}
+
 #[derive(Debug, Serialize)]
 pub enum OutputRecord {
     BT(BitcoinTax),
@@ -149,21 +248,27 @@ pub struct BitcoinTax {
     date: NaiveDateTime,
     action: String,
     account: String,
-    symbol: Coin,
-    volume: f64,
+    symbol: String,
+    volume: Decimal,
+    #[serde(rename = "Value")]
+    value: Option<Decimal>,
 }
 
 impl BitcoinTax {
-    pub fn create(date: NaiveDateTime, volume: f64, symbol: Coin) -> Self {
-        let coin: String = symbol.into();
-        let account = format!("{} STAKING", coin);
-
+    pub fn create(
+        date: NaiveDateTime,
+        volume: Decimal,
+        symbol: Coin,
+        value: Option<Decimal>,
+        config: &Config,
+    ) -> Self {
         Self {
             date,
             action: "INCOME".into(),
-            account,
-            symbol,
+            account: config.account(&symbol),
+            symbol: config.symbol(&symbol),
             volume,
+            value,
         }
     }
 }
@@ -173,15 +278,15 @@ pub struct CoinTracking {
     #[serde(rename = "Type")]
     tx_type: String,
     #[serde(rename = "Buy Amount")]
-    buy_amount: f64,
+    buy_amount: Decimal,
     #[serde(rename = "Buy Currency")]
     buy_currency: String,
     #[serde(rename = "Sell Amount")]
-    sell_amount: f64,
+    sell_amount: Decimal,
     #[serde(rename = "Sell Currency")]
     sell_currency: Currency,
     #[serde(rename = "Fee")]
-    fee: f64,
+    fee: Decimal,
     #[serde(rename = "Fee Currency")]
     fee_currency: Currency,
     #[serde(rename = "Exchange")]
@@ -195,42 +300,147 @@ pub struct CoinTracking {
     #[serde(rename = "Tx-ID")]
     tx_id: String,
     #[serde(rename = "Buy Value in Account Currency")]
-    buy_value: f64,
+    buy_value: Decimal,
 }
 
 impl CoinTracking {
     pub fn create(
-        buy_amount: f64,
-        buy_currency: String,
-        trade_group: String,
-        comment: String,
+        buy_amount: Decimal,
+        symbol: Coin,
         date: NaiveDateTime,
+        buy_value: Decimal,
+        config: &Config,
     ) -> Self {
         Self {
             tx_type: "Income".into(),
             buy_amount,
-            buy_currency,
-            sell_amount: 0.0,
-            sell_currency: Currency::USD,
-            fee: 0.0,
-            fee_currency: Currency::USD,
+            buy_currency: config.symbol(&symbol),
+            sell_amount: Decimal::ZERO,
+            sell_currency: config.account_currency,
+            fee: Decimal::ZERO,
+            fee_currency: config.account_currency,
             exchange: "".into(),
-            trade_group,
-            comment,
+            trade_group: config.trade_group(&symbol),
+            comment: config.comment(&symbol),
             date,
             tx_id: "".into(),
-            buy_value: 0.0,
+            buy_value,
         }
     }
 }
 
-#[derive(Debug, Serialize)]
-enum Currency {
+/// A single posting of a double-entry [`Transaction`].
+#[derive(Debug)]
+pub struct Posting {
+    pub account: String,
+    pub amount: Decimal,
+    pub currency: String,
+    /// Optional total-price annotation, rendered as `@@ amount currency`.
+    pub cost: Option<(Decimal, String)>,
+}
+
+/// A double-entry transaction rendered as plaintext-accounting
+/// (ledger/beancount) text, one credit/debit pair per staking reward.
+#[derive(Debug)]
+pub struct Transaction {
+    pub date: NaiveDateTime,
+    pub narration: String,
+    pub postings: Vec<Posting>,
+}
+
+impl Transaction {
+    /// Build the credit-income / debit-asset pair for a single reward. The
+    /// asset leg always carries the reward quantity in the coin; when a fiat
+    /// value is known it rides along as a total-price annotation
+    /// (`5 DOT @@ 24.69 USD`, the `@@` syntax both hledger and beancount accept)
+    /// and the income leg is denominated in the fiat currency (`-24.69 USD`) so
+    /// the two legs weigh equal-and-opposite and the transaction balances.
+    /// Without a fiat value both legs stay in the coin (`±5 DOT`), which already
+    /// balances.
+    pub fn staking_reward(
+        date: NaiveDateTime,
+        volume: Decimal,
+        symbol: Coin,
+        value: Option<Decimal>,
+        config: &Config,
+    ) -> Self {
+        let coin = config.symbol(&symbol);
+        let income = format!("{}:{}", config.income_prefix, coin);
+        let asset = format!("{}:{}", config.asset_prefix, coin);
+
+        let currency = config.account_currency.code().to_string();
+        let cost = value.map(|value| (value, currency.clone()));
+
+        // Balance the income leg against the asset leg's cost: in the fiat
+        // value it, otherwise mirror the coin quantity.
+        let income_posting = match value {
+            Some(value) => Posting {
+                account: income,
+                amount: -value,
+                currency,
+                cost: None,
+            },
+            None => Posting {
+                account: income,
+                amount: -volume,
+                currency: coin.clone(),
+                cost: None,
+            },
+        };
+
+        Self {
+            date,
+            narration: "Staking reward".into(),
+            postings: vec![
+                Posting {
+                    account: asset,
+                    amount: volume,
+                    currency: coin,
+                    cost,
+                },
+                income_posting,
+            ],
+        }
+    }
+}
+
+impl fmt::Display for Transaction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{} * \"{}\"", self.date.format("%Y-%m-%d"), self.narration)?;
+        for posting in &self.postings {
+            write!(
+                f,
+                "    {}  {} {}",
+                posting.account, posting.amount, posting.currency
+            )?;
+            if let Some((amount, currency)) = &posting.cost {
+                write!(f, " @@ {} {}", amount, currency)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub enum Currency {
+    #[default]
     USD,
     GBP,
     EUR,
 }
 
+impl Currency {
+    /// ISO-style ticker used in ledger output.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Currency::USD => "USD",
+            Currency::GBP => "GBP",
+            Currency::EUR => "EUR",
+        }
+    }
+}
+
 impl FromStr for Currency {
     type Err = AnyError;
 
@@ -244,65 +454,224 @@ impl FromStr for Currency {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
-pub enum Coin {
-    DOT,
-    KSM,
-    ATOM,
-    ETH,
-    SOL,
-    KAVA,
-    ADA,
-    XTZ,
+/// Generate the [`Coin`] registry from one table of
+/// `VARIANT => [aliases...] => "OUTPUT SYMBOL" => "coingecko-id"` rows. A single
+/// invocation expands to the enum (plus an `Unknown(String)` catch-all) and its
+/// `FromStr`, `From<String>` and `From<Coin> for String` conversions, along
+/// with the canonical ticker and CoinGecko id lookups price sources key on, so
+/// onboarding a coin means adding one line here rather than editing several
+/// match arms.
+macro_rules! make_coin {
+    ( $( $variant:ident => [ $( $alias:literal ),+ ] => $symbol:literal => $id:literal ),+ $(,)? ) => {
+        #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+        pub enum Coin {
+            $( $variant, )+
+            /// A ticker we don't recognise, preserved verbatim.
+            Unknown(String),
+        }
+
+        impl FromStr for Coin {
+            type Err = AnyError;
+
+            fn from_str(s: &str) -> Result<Coin, AnyError> {
+                Ok(match &s.to_lowercase()[..] {
+                    $( $( $alias )|+ => Coin::$variant, )+
+                    other => Coin::Unknown(other.to_string()),
+                })
+            }
+        }
+
+        impl From<String> for Coin {
+            fn from(s: String) -> Self {
+                // Unrecognised tickers become `Coin::Unknown` without panicking.
+                Coin::from_str(&s).unwrap_or_else(|_| Coin::Unknown(s))
+            }
+        }
+
+        impl From<Coin> for String {
+            fn from(coin: Coin) -> String {
+                match coin {
+                    $( Coin::$variant => String::from($symbol), )+
+                    Coin::Unknown(ticker) => ticker,
+                }
+            }
+        }
+
+        impl Coin {
+            /// Canonical ticker (the enum variant name) used when querying price
+            /// APIs that key on a symbol. `None` for unrecognised coins.
+            pub fn ticker(&self) -> Option<&'static str> {
+                match self {
+                    $( Coin::$variant => Some(stringify!($variant)), )+
+                    Coin::Unknown(_) => None,
+                }
+            }
+
+            /// CoinGecko coin id (e.g. `polkadot`) for the `/coins/{id}/history`
+            /// endpoint. `None` for unrecognised coins.
+            pub fn coingecko_id(&self) -> Option<&'static str> {
+                match self {
+                    $( Coin::$variant => Some($id), )+
+                    Coin::Unknown(_) => None,
+                }
+            }
+        }
+    };
 }
 
-impl FromStr for Coin {
-    type Err = AnyError;
+make_coin! {
+    DOT => ["dot", "dot.s"] => "DOT2" => "polkadot",
+    KSM => ["ksm", "ksm.s"] => "KSM" => "kusama",
+    ATOM => ["atom", "atom.s"] => "ATOM" => "cosmos",
+    ETH => ["eth", "eth.s", "eth2", "eth2.s"] => "ETH" => "ethereum",
+    SOL => ["sol", "sol.s"] => "SOL" => "solana",
+    KAVA => ["kava", "kava.s"] => "KAVA" => "kava",
+    ADA => ["ada", "ada.s"] => "ADA" => "cardano",
+    XTZ => ["xtz", "xtz.s"] => "XTZ" => "tezos",
+}
 
-    fn from_str(s: &str) -> Result<Coin, AnyError> {
-        match &s.to_lowercase()[..] {
-            "dot" | "dot.s" => Ok(Coin::DOT),
-            "ksm" | "ksm.s" => Ok(Coin::KSM),
-            "atom" | "atom.s" => Ok(Coin::ATOM),
-            "eth" | "eth.s" | "eth2" | "eth2.s" => Ok(Coin::ETH),
-            "sol" | "sol.s" => Ok(Coin::SOL),
-            "kava" | "kava.s" => Ok(Coin::KAVA),
-            "ada" | "ada.s" => Ok(Coin::ADA),
-            "xtz" | "xtz.s" => Ok(Coin::XTZ),
-            _ => panic!("Invalid coin type!"),
+impl Coin {
+    /// Whether this ticker was recognised by the registry.
+    pub fn is_unknown(&self) -> bool {
+        matches!(self, Coin::Unknown(_))
+    }
+}
+
+/// Deserialize a [`Coin`] from its ticker string. Unrecognised tickers map to
+/// `Coin::Unknown` rather than failing, so the caller can skip the row and warn
+/// instead of aborting the whole run.
+impl<'de> Deserialize<'de> for Coin {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct CoinVisitor;
+
+        impl de::Visitor<'_> for CoinVisitor {
+            type Value = Coin;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a coin ticker string")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<Coin, E>
+            where
+                E: de::Error,
+            {
+                // Unknown tickers yield `Coin::Unknown` rather than erroring.
+                Ok(Coin::from_str(value).unwrap_or_else(|_| Coin::Unknown(value.to_string())))
+            }
         }
+
+        deserializer.deserialize_str(CoinVisitor)
     }
 }
 
-impl From<String> for Coin {
-    fn from(s: String) -> Self {
-        match &s.to_lowercase()[..] {
-            "dot" | "dot.s" => Coin::DOT,
-            "ksm" | "ksm.s" => Coin::KSM,
-            "atom" | "atom.s" => Coin::ATOM,
-            "eth" | "eth.s" | "eth2" | "eth2.s" => Coin::ETH,
-            "sol" | "sol.s" => Coin::SOL,
-            "kava" | "kava.s" => Coin::KAVA,
-            "ada" | "ada.s" => Coin::ADA,
-            "xtz" | "xtz.s" => Coin::XTZ,
-            _ => panic!("Invalid coin type!"),
+/// User-supplied overrides for the account names, trade groups, comments and
+/// output symbols that are otherwise hardcoded. Loaded from a `taxmat.toml` or
+/// `taxmat.ron` file via `--config`; every field has a `#[serde(default)]` so a
+/// partial file only overrides what it mentions.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Account currency fiat values and `CoinTracking` currency columns use.
+    pub account_currency: Currency,
+    /// Per-coin output symbol overrides, e.g. `DOT = "DOT2"`.
+    pub symbols: HashMap<Coin, String>,
+    /// Per-coin account-name templates; `{coin}` expands to the output symbol.
+    pub accounts: HashMap<Coin, String>,
+    /// Per-coin trade-group overrides.
+    pub trade_groups: HashMap<Coin, String>,
+    /// Per-coin comment overrides.
+    pub comments: HashMap<Coin, String>,
+    /// Extra input ticker aliases mapping onto a known coin.
+    pub aliases: HashMap<String, Coin>,
+    /// Account-name template used when a coin has no `accounts` entry.
+    pub default_account: String,
+    /// Trade group used when a coin has no `trade_groups` entry.
+    pub default_trade_group: String,
+    /// Comment used when a coin has no `comments` entry.
+    pub default_comment: String,
+    /// Account prefix for the income leg of ledger output.
+    pub income_prefix: String,
+    /// Account prefix for the asset leg of ledger output.
+    pub asset_prefix: String,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            account_currency: Currency::USD,
+            symbols: HashMap::new(),
+            accounts: HashMap::new(),
+            trade_groups: HashMap::new(),
+            comments: HashMap::new(),
+            aliases: HashMap::new(),
+            default_account: "{coin} STAKING".into(),
+            default_trade_group: "Polkadot Staking".into(),
+            default_comment: "Self-Staking".into(),
+            income_prefix: "Income:Staking".into(),
+            asset_prefix: "Assets:Crypto".into(),
         }
     }
 }
 
-impl Into<String> for Coin {
-    fn into(self) -> String {
-        match self {
-            Coin::DOT => String::from("DOT2"),
-            Coin::KSM => String::from("KSM"),
-            Coin::ATOM => String::from("ATOM"),
-            Coin::ETH => String::from("ETH"),
-            Coin::SOL => String::from("SOL"),
-            Coin::KAVA => String::from("KAVA"),
-            Coin::ADA => String::from("ADA"),
-            Coin::XTZ => String::from("XTZ"),
+impl Config {
+    /// Load a config from a `.toml` or `.ron` file, dispatching on extension.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Ok(toml::from_str(&contents)?),
+            Some("ron") => Ok(ron::from_str(&contents)?),
+            _ => bail!("Config file must have a .toml or .ron extension"),
         }
     }
+
+    /// Resolved output symbol for `coin`, honouring any override.
+    pub fn symbol(&self, coin: &Coin) -> String {
+        self.symbols
+            .get(coin)
+            .cloned()
+            .unwrap_or_else(|| coin.clone().into())
+    }
+
+    /// Resolved account name for `coin`.
+    pub fn account(&self, coin: &Coin) -> String {
+        let template = self.accounts.get(coin).unwrap_or(&self.default_account);
+        self.render(template, coin)
+    }
+
+    /// Resolved trade group for `coin`.
+    pub fn trade_group(&self, coin: &Coin) -> String {
+        let template = self
+            .trade_groups
+            .get(coin)
+            .unwrap_or(&self.default_trade_group);
+        self.render(template, coin)
+    }
+
+    /// Resolved comment for `coin`.
+    pub fn comment(&self, coin: &Coin) -> String {
+        let template = self.comments.get(coin).unwrap_or(&self.default_comment);
+        self.render(template, coin)
+    }
+
+    /// Resolve an input ticker to a coin, consulting extra aliases first.
+    /// Returns `None` for tickers that neither alias nor registry recognise.
+    pub fn resolve_coin(&self, ticker: &str) -> Option<Coin> {
+        if let Some(coin) = self.aliases.get(&ticker.to_lowercase()) {
+            return Some(coin.clone());
+        }
+        match ticker.parse::<Coin>() {
+            Ok(coin) if !coin.is_unknown() => Some(coin),
+            _ => None,
+        }
+    }
+
+    fn render(&self, template: &str, coin: &Coin) -> String {
+        template.replace("{coin}", &self.symbol(coin))
+    }
 }
 
 /// Deserialize bool from String with custom value mapping
@@ -319,3 +688,122 @@ where
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use rust_decimal::Decimal;
+
+    fn dt(y: i32, m: u32, d: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+    }
+
+    fn dt_hms(y: i32, m: u32, d: u32, h: u32, mi: u32, s: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, mi, s)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_date_tries_formats_in_order() {
+        let formats = [
+            DateFormat::Naive("%d/%m/%Y %H:%M:%S"),
+            DateFormat::Naive("%Y-%m-%d %H:%M:%S"),
+        ];
+        let parsed = parse_date("2021-03-15 12:00:00", &formats).unwrap();
+        assert_eq!(parsed, dt_hms(2021, 3, 15, 12, 0, 0));
+    }
+
+    #[test]
+    fn parse_date_handles_twelve_hour_and_rfc3339() {
+        let twelve = [DateFormat::Naive("%m/%d/%Y, %I:%M:%S %p")];
+        let parsed = parse_date("2/25/2021, 2:24:46 PM", &twelve).unwrap();
+        assert_eq!(parsed, dt_hms(2021, 2, 25, 14, 24, 46));
+
+        // RFC 3339 offsets are normalized to UTC.
+        let parsed = parse_date("2021-03-15T12:00:00+02:00", &[DateFormat::Rfc3339]).unwrap();
+        assert_eq!(parsed, dt_hms(2021, 3, 15, 10, 0, 0));
+    }
+
+    #[test]
+    fn parse_date_errors_when_nothing_matches() {
+        assert!(parse_date("not a date", &[DateFormat::Naive("%Y-%m-%d %H:%M:%S")]).is_err());
+    }
+
+    #[test]
+    fn period_label_buckets() {
+        let date = dt(2021, 8, 3);
+        assert_eq!(Period::Month.label(date), "2021-08");
+        assert_eq!(Period::Quarter.label(date), "2021-Q3");
+        assert_eq!(Period::HalfYear.label(date), "2021-H2");
+        assert_eq!(Period::Quarter.label(dt(2021, 1, 1)), "2021-Q1");
+        assert_eq!(Period::HalfYear.label(dt(2021, 6, 30)), "2021-H1");
+    }
+
+    #[test]
+    fn coin_round_trips_and_falls_back() {
+        assert_eq!("dot".parse::<Coin>().unwrap(), Coin::DOT);
+        assert_eq!("dot.s".parse::<Coin>().unwrap(), Coin::DOT);
+        assert_eq!("ETH2".parse::<Coin>().unwrap(), Coin::ETH);
+
+        let unknown = "wtf".parse::<Coin>().unwrap();
+        assert_eq!(unknown, Coin::Unknown("wtf".to_string()));
+        assert!(unknown.is_unknown());
+
+        let symbol: String = Coin::DOT.into();
+        assert_eq!(symbol, "DOT2");
+        let passthrough: String = Coin::Unknown("wtf".to_string()).into();
+        assert_eq!(passthrough, "wtf");
+
+        // From<String> never panics on unknown input.
+        assert_eq!(Coin::from("zzz".to_string()), Coin::Unknown("zzz".to_string()));
+    }
+
+    #[test]
+    fn config_renders_templates_and_resolves_aliases() {
+        let mut config = Config::default();
+        assert_eq!(config.symbol(&Coin::DOT), "DOT2");
+        assert_eq!(config.account(&Coin::DOT), "DOT2 STAKING");
+        assert_eq!(config.trade_group(&Coin::DOT), "Polkadot Staking");
+
+        config.symbols.insert(Coin::DOT, "DOT".to_string());
+        config.accounts.insert(Coin::DOT, "Staking {coin}".to_string());
+        assert_eq!(config.symbol(&Coin::DOT), "DOT");
+        assert_eq!(config.account(&Coin::DOT), "Staking DOT");
+
+        config.aliases.insert("polkadot".to_string(), Coin::DOT);
+        assert_eq!(config.resolve_coin("polkadot"), Some(Coin::DOT));
+        assert_eq!(config.resolve_coin("ksm"), Some(Coin::KSM));
+        assert_eq!(config.resolve_coin("nope"), None);
+    }
+
+    #[test]
+    fn transaction_display_keeps_quantity_and_annotates_cost() {
+        let config = Config::default();
+        let date = dt(2021, 1, 1);
+
+        let with_fiat = Transaction::staking_reward(
+            date,
+            Decimal::new(5, 0),
+            Coin::DOT,
+            Some(Decimal::new(2469, 2)),
+            &config,
+        )
+        .to_string();
+        assert!(with_fiat.contains("Assets:Crypto:DOT2  5 DOT2 @@ 24.69 USD"));
+        // With a fiat value the income leg is denominated in the fiat currency
+        // so the transaction balances against the priced asset leg.
+        assert!(with_fiat.contains("Income:Staking:DOT2  -24.69 USD"));
+
+        let without_fiat =
+            Transaction::staking_reward(date, Decimal::new(5, 0), Coin::DOT, None, &config)
+                .to_string();
+        assert!(without_fiat.contains("Assets:Crypto:DOT2  5 DOT2\n"));
+        assert!(!without_fiat.contains("{{"));
+    }
+}
@@ -0,0 +1,3 @@
+pub mod formats;
+pub mod opt;
+pub mod price;
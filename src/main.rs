@@ -1,14 +1,32 @@
 use anyhow::{anyhow, Result};
 use chrono::prelude::*;
 use csv::Terminator;
+use prettytable::{row, Table};
+use rayon::prelude::*;
+use rust_decimal::Decimal;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
 use std::process;
 use structopt::StructOpt;
 
 use taxmat::formats::*;
 use taxmat::opt::Opt;
+use taxmat::price::PriceProvider;
 
 fn main() -> Result<()> {
-    let options = Opt::from_args();
+    let mut options = Opt::from_args();
+
+    let config = match &options.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+
+    let mut provider = match options.price_source.take() {
+        Some(source) => Some(source.into_provider(config.account_currency)?),
+        None => None,
+    };
 
     let input_format: InputFormat = match &options.input_format.to_lowercase()[..] {
         "subscan" => InputFormat::Subscan,
@@ -23,38 +41,271 @@ fn main() -> Result<()> {
     let output_format: OutputFormat = match &options.output_format.to_lowercase()[..] {
         "bitcointax" | "bitcoin.tax" => OutputFormat::BitcoinTax,
         "cointracking" | "coin tracking" => OutputFormat::CoinTracking,
+        "ledger" | "beancount" => OutputFormat::Ledger,
         _ => {
             println!("Invalid output format!");
             process::exit(1);
         }
     };
 
+    let provider = provider.as_deref_mut();
+
+    if options.summary {
+        let rows = match input_format {
+            InputFormat::Subscan => collect_records::<Subscan>(&options, provider)?,
+            InputFormat::Kraken => collect_kraken(&options, provider, &config)?,
+            InputFormat::StakeTax => collect_staketax(&options, provider)?,
+        };
+
+        print_summary(&rows, options.period, &config);
+        return Ok(());
+    }
+
     match input_format {
-        InputFormat::Subscan => parse_records::<Subscan>(&options, &output_format)?,
+        InputFormat::Subscan => {
+            parse_records::<Subscan>(&options, &output_format, provider, &config)?
+        }
         // Kraken files have multiple types of coins
-        InputFormat::Kraken => parse_kraken_file(&options, &output_format)?,
-        InputFormat::StakeTax => parse_staketax(&options, &output_format)?,
+        InputFormat::Kraken => parse_kraken_file(&options, &output_format, provider, &config)?,
+        InputFormat::StakeTax => parse_staketax(&options, &output_format, provider, &config)?,
+    }
+
+    Ok(())
+}
+
+/// Look up the fiat value of `volume` units of `coin` at `date`, returning
+/// `None` when no price source is configured or the source has no quote.
+fn fiat_value(
+    provider: Option<&mut (dyn PriceProvider + '_)>,
+    coin: &Coin,
+    date: NaiveDateTime,
+    volume: Decimal,
+) -> Result<Option<Decimal>> {
+    match provider {
+        Some(provider) => Ok(provider
+            .price_on(coin, date.date())?
+            .map(|price| price * volume)),
+        None => Ok(None),
+    }
+}
+
+/// A single filtered staking reward, flattened across input formats so the
+/// summary report can aggregate them uniformly.
+struct RewardRow {
+    date: NaiveDateTime,
+    coin: Coin,
+    amount: Decimal,
+    value: Option<Decimal>,
+}
+
+/// Emit the collected staking rewards as double-entry ledger/beancount text.
+/// Shared by every input format: each parser collects its filtered
+/// [`RewardRow`]s and feeds them here, so the per-record emission lives in one
+/// place rather than being copy-pasted per format.
+fn write_ledger(path: &Path, rows: &[RewardRow], config: &Config) -> Result<()> {
+    let mut wtr = BufWriter::new(File::create(path)?);
+
+    for row in rows {
+        let tx =
+            Transaction::staking_reward(row.date, row.amount, row.coin.clone(), row.value, config);
+        write!(wtr, "{}", tx)?;
     }
 
     Ok(())
 }
 
-fn parse_records<D: InputRecord + serde::de::DeserializeOwned>(
+fn collect_records<D: InputRecord + serde::de::DeserializeOwned + Sync>(
+    options: &Opt,
+    mut provider: Option<&mut (dyn PriceProvider + '_)>,
+) -> Result<Vec<RewardRow>> {
+    let symbol = &options.coin;
+    let (start_date, end_date) = get_date_range(options)?;
+
+    let mut rdr = csv::Reader::from_path(&options.input)?;
+    let records: Vec<D> = rdr.deserialize().collect::<std::result::Result<_, _>>()?;
+
+    // Parse timestamps and range-filter in parallel.
+    let parsed: Vec<(NaiveDateTime, Decimal)> = records
+        .par_iter()
+        .map(|res| Ok::<_, anyhow::Error>((parse_date(res.get_date(), D::date_formats())?, res.get_amount())))
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|(date, _)| (start_date <= *date) && (*date <= end_date))
+        .collect();
+
+    // Fiat lookups share a mutable, cached provider, so stay sequential.
+    let mut rows = Vec::with_capacity(parsed.len());
+    for (date, amount) in parsed {
+        let value = fiat_value(provider.as_deref_mut(), symbol, date, amount)?;
+        rows.push(RewardRow {
+            date,
+            coin: symbol.clone(),
+            amount,
+            value,
+        });
+    }
+
+    Ok(rows)
+}
+
+fn collect_staketax(
+    options: &Opt,
+    mut provider: Option<&mut (dyn PriceProvider + '_)>,
+) -> Result<Vec<RewardRow>> {
+    let symbol = &options.coin;
+    let (start_date, end_date) = get_date_range(options)?;
+
+    let mut rdr = csv::Reader::from_path(&options.input)?;
+    let records: Vec<StakeTax> = rdr.deserialize().collect::<std::result::Result<_, _>>()?;
+
+    // Parse timestamps and filter staking rows in parallel.
+    let parsed: Vec<(NaiveDateTime, Decimal)> = records
+        .par_iter()
+        .map(|res| {
+            let date = parse_date(res.get_date(), StakeTax::date_formats())?;
+            Ok::<_, anyhow::Error>((date, res.get_amount(), res.tx_type == "STAKING"))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|(date, _, staking)| *staking && (start_date <= *date) && (*date <= end_date))
+        .map(|(date, amount, _)| (date, amount))
+        .collect();
+
+    let mut rows = Vec::with_capacity(parsed.len());
+    for (date, amount) in parsed {
+        let value = fiat_value(provider.as_deref_mut(), symbol, date, amount)?;
+        rows.push(RewardRow {
+            date,
+            coin: symbol.clone(),
+            amount,
+            value,
+        });
+    }
+
+    Ok(rows)
+}
+
+fn collect_kraken(
+    options: &Opt,
+    mut provider: Option<&mut (dyn PriceProvider + '_)>,
+    config: &Config,
+) -> Result<Vec<RewardRow>> {
+    let (start_date, end_date) = get_date_range(options)?;
+
+    let mut rdr = csv::Reader::from_path(&options.input)?;
+    let records: Vec<Kraken> = rdr.deserialize().collect::<std::result::Result<_, _>>()?;
+
+    // Parse timestamps and filter staking rows in parallel, then resolve coins.
+    let parsed: Vec<(NaiveDateTime, Coin, Decimal)> = records
+        .par_iter()
+        .map(|res| {
+            let date = parse_date(res.get_date(), Kraken::date_formats())?;
+            Ok::<_, anyhow::Error>((date, res.action == "staking", res.asset.clone(), res.get_amount()))
+        })
+        .collect::<Result<Vec<_>>>()?
+        .into_iter()
+        .filter(|(date, staking, _, _)| *staking && (start_date <= *date) && (*date <= end_date))
+        .filter_map(|(date, _, asset, amount)| match config.resolve_coin(&asset) {
+            Some(coin) => Some((date, coin, amount)),
+            None => {
+                eprintln!("Skipping row with unrecognized coin: {}", asset);
+                None
+            }
+        })
+        .collect();
+
+    let mut rows = Vec::with_capacity(parsed.len());
+    for (date, coin, amount) in parsed {
+        let value = fiat_value(provider.as_deref_mut(), &coin, date, amount)?;
+        rows.push(RewardRow {
+            date,
+            coin,
+            amount,
+            value,
+        });
+    }
+
+    Ok(rows)
+}
+
+/// Aggregate rewards by period and coin and print a subtotal table, plus a
+/// grand-total income line when fiat values are available.
+fn print_summary(rows: &[RewardRow], period: Period, config: &Config) {
+    // (period label, output symbol) -> (volume, income)
+    let totals = rows
+        .par_iter()
+        .fold(BTreeMap::new, |mut acc, row| {
+            let key = (period.label(row.date), config.symbol(&row.coin));
+            let entry = acc.entry(key).or_insert((Decimal::ZERO, Decimal::ZERO));
+            entry.0 += row.amount;
+            entry.1 += row.value.unwrap_or(Decimal::ZERO);
+            acc
+        })
+        .reduce(BTreeMap::new, |mut a, b| {
+            for (key, (volume, income)) in b {
+                let entry = a.entry(key).or_insert((Decimal::ZERO, Decimal::ZERO));
+                entry.0 += volume;
+                entry.1 += income;
+            }
+            a
+        });
+
+    let has_fiat = rows.iter().any(|row| row.value.is_some());
+    let mut total_income = Decimal::ZERO;
+
+    let mut table = Table::new();
+    if has_fiat {
+        table.add_row(row![
+            "Period",
+            "Coin",
+            "Volume",
+            format!("Income ({})", config.account_currency.code())
+        ]);
+    } else {
+        table.add_row(row!["Period", "Coin", "Volume"]);
+    }
+
+    for ((label, coin), (volume, income)) in &totals {
+        total_income += income;
+        if has_fiat {
+            table.add_row(row![label, coin, volume, income]);
+        } else {
+            table.add_row(row![label, coin, volume]);
+        }
+    }
+
+    if has_fiat {
+        table.add_row(row!["TOTAL", "", "", total_income]);
+    }
+
+    table.printstd();
+}
+
+fn parse_records<D: InputRecord + serde::de::DeserializeOwned + Sync>(
     options: &Opt,
     output_format: &OutputFormat,
+    mut provider: Option<&mut (dyn PriceProvider + '_)>,
+    config: &Config,
 ) -> Result<()> {
-    let symbol = options.coin;
+    let symbol = &options.coin;
 
     println!("symbol: {symbol:?}");
 
+    // Ledger/beancount output is double-entry text, not flat CSV rows.
+    if let OutputFormat::Ledger = output_format {
+        let rows = collect_records::<D>(options, provider)?;
+        return write_ledger(&options.output, &rows, config);
+    }
+
     let (start_date, end_date) = get_date_range(options)?;
 
     let mut rdr = csv::Reader::from_path(&options.input)?;
+
     let mut wtr = csv::Writer::from_path(&options.output)?;
 
     match output_format {
         OutputFormat::BitcoinTax => {
-            wtr.write_record(["Date", "Action", "Account", "Symbol", "Volume"])?;
+            wtr.write_record(["Date", "Action", "Account", "Symbol", "Volume", "Value"])?;
         }
         OutputFormat::CoinTracking => {
             wtr.write_record([
@@ -73,25 +324,30 @@ fn parse_records<D: InputRecord + serde::de::DeserializeOwned>(
                 "Buy Value in Account Currency",
             ])?;
         }
+        OutputFormat::Ledger => unreachable!("ledger output handled above"),
     }
 
     for result in rdr.deserialize() {
         let res: D = result?;
 
-        let date = NaiveDateTime::parse_from_str(&res.get_date()[..], "%Y-%m-%d %H:%M:%S")?;
+        let date = parse_date(res.get_date(), D::date_formats())?;
 
         if (start_date <= date) && (date <= end_date) {
+            let amount = res.get_amount();
+            let value = fiat_value(provider.as_deref_mut(), symbol, date, amount)?;
+
             let record = match output_format {
                 OutputFormat::BitcoinTax => {
-                    OutputRecord::BT(BitcoinTax::create(date, res.get_amount(), symbol))
+                    OutputRecord::BT(BitcoinTax::create(date, amount, symbol.clone(), value, config))
                 }
                 OutputFormat::CoinTracking => OutputRecord::CT(CoinTracking::create(
-                    res.get_amount(),
-                    symbol.into(),
-                    "Polkadot Staking".into(),
-                    "Self-Staking".to_string(),
+                    amount,
+                    symbol.clone(),
                     date,
+                    value.unwrap_or(Decimal::ZERO),
+                    config,
                 )),
+                OutputFormat::Ledger => unreachable!("ledger output handled above"),
             };
 
             wtr.serialize(record)?;
@@ -101,12 +357,24 @@ fn parse_records<D: InputRecord + serde::de::DeserializeOwned>(
     Ok(())
 }
 
-fn parse_staketax(options: &Opt, output_format: &OutputFormat) -> Result<()> {
-    let symbol = options.coin;
+fn parse_staketax(
+    options: &Opt,
+    output_format: &OutputFormat,
+    mut provider: Option<&mut (dyn PriceProvider + '_)>,
+    config: &Config,
+) -> Result<()> {
+    let symbol = &options.coin;
+
+    // Ledger/beancount output is double-entry text, not flat CSV rows.
+    if let OutputFormat::Ledger = output_format {
+        let rows = collect_staketax(options, provider)?;
+        return write_ledger(&options.output, &rows, config);
+    }
 
     let (start_date, end_date) = get_date_range(options)?;
 
     let mut rdr = csv::Reader::from_path(&options.input)?;
+
     let mut wtr = csv::WriterBuilder::new()
         .terminator(Terminator::CRLF)
         .from_path(&options.output)?;
@@ -114,12 +382,15 @@ fn parse_staketax(options: &Opt, output_format: &OutputFormat) -> Result<()> {
     for result in rdr.deserialize() {
         let res: StakeTax = result?;
 
-        let date = NaiveDateTime::parse_from_str(&res.get_date()[..], "%Y-%m-%d %H:%M:%S")?;
+        let date = parse_date(res.get_date(), StakeTax::date_formats())?;
 
         if (start_date <= date) && (date <= end_date) && (res.tx_type == "STAKING") {
+            let amount = res.get_amount();
+            let value = fiat_value(provider.as_deref_mut(), symbol, date, amount)?;
+
             let record = match output_format {
                 OutputFormat::BitcoinTax => {
-                    OutputRecord::BT(BitcoinTax::create(date, res.get_amount(), symbol))
+                    OutputRecord::BT(BitcoinTax::create(date, amount, symbol.clone(), value, config))
                 }
                 _ => panic!("Not currently supported"),
             };
@@ -131,32 +402,45 @@ fn parse_staketax(options: &Opt, output_format: &OutputFormat) -> Result<()> {
     Ok(())
 }
 
-fn parse_kraken_file(options: &Opt, output_format: &OutputFormat) -> Result<()> {
+fn parse_kraken_file(
+    options: &Opt,
+    output_format: &OutputFormat,
+    mut provider: Option<&mut (dyn PriceProvider + '_)>,
+    config: &Config,
+) -> Result<()> {
     // let symbol = options.coin;
 
+    // Ledger/beancount output is double-entry text, not flat CSV rows.
+    if let OutputFormat::Ledger = output_format {
+        let rows = collect_kraken(options, provider, config)?;
+        return write_ledger(&options.output, &rows, config);
+    }
+
     let (start_date, end_date) = get_date_range(options)?;
 
     let mut rdr = csv::Reader::from_path(&options.input)?;
+
     let mut wtr = csv::Writer::from_path(&options.output)?;
 
     for result in rdr.deserialize() {
         let res: Kraken = result?;
 
-        let date = NaiveDateTime::parse_from_str(&res.get_date()[..], "%Y-%m-%d %H:%M:%S")?;
+        let date = parse_date(res.get_date(), Kraken::date_formats())?;
 
         if (start_date <= date) && (date <= end_date) && res.action == "staking" {
-            let coin_opt = res.asset.parse::<Coin>();
-
-            let coin = match coin_opt {
-                Ok(coin) => coin,
-                Err(e) => {
-                    println!("Invalid coin: {}", res.asset);
-                    panic!("{}", e);
+            let coin = match config.resolve_coin(&res.asset) {
+                Some(coin) => coin,
+                None => {
+                    eprintln!("Skipping row with unrecognized coin: {}", res.asset);
+                    continue;
                 }
             };
+            let amount = res.get_amount();
+            let value = fiat_value(provider.as_deref_mut(), &coin, date, amount)?;
+
             let record = match output_format {
                 OutputFormat::BitcoinTax => {
-                    OutputRecord::BT(BitcoinTax::create(date, res.get_amount(), coin))
+                    OutputRecord::BT(BitcoinTax::create(date, amount, coin, value, config))
                 }
                 _ => panic!("Not currently supported"),
             };
@@ -2,6 +2,7 @@ use std::path::PathBuf;
 use structopt::StructOpt;
 
 use crate::formats::*;
+use crate::price::PriceSource;
 
 #[derive(Debug, StructOpt)]
 #[structopt(name = "taxmat", about = "Polkadot staking csv tax formatter.")]
@@ -33,4 +34,24 @@ pub struct Opt {
     /// year's quarter to parse results
     #[structopt(short, long, default_value = "all")]
     pub quarter: Quarter,
+
+    /// historical price source for fiat cost basis, e.g.
+    /// `csv:prices.csv`, `coingecko:<demo-key>`, `coingecko-pro:<pro-key>` or
+    /// `alphavantage:<api-key>`
+    #[structopt(short = "p", long)]
+    pub price_source: Option<PriceSource>,
+
+    /// path to a taxmat.toml / taxmat.ron config file with account, trade-group
+    /// and output-symbol overrides
+    #[structopt(long, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// print an aggregated per-coin, per-period summary instead of writing the
+    /// output file
+    #[structopt(short = "s", long)]
+    pub summary: bool,
+
+    /// subtotal grouping used by `--summary`: month, quarter or half-year
+    #[structopt(long, default_value = "quarter")]
+    pub period: Period,
 }
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::formats::{Coin, Currency};
+
+/// A source of historical spot prices for a coin, denominated in the account
+/// currency. Implementors look up the close price for a given coin on a given
+/// day; returning `None` means the source simply had no quote for that day.
+pub trait PriceProvider {
+    /// Close price of `coin` on `date`, in the account currency.
+    fn price_on(&mut self, coin: &Coin, date: NaiveDate) -> Result<Option<Decimal>>;
+}
+
+/// How the user asked us to resolve historical prices. Parsed from the
+/// `--price-source` option, e.g. `csv:prices.csv`, `coingecko:<key>` or
+/// `alphavantage:<key>`.
+#[derive(Debug)]
+pub enum PriceSource {
+    Csv(PathBuf),
+    /// CoinGecko, with `pro` selecting the paid `pro-api` host and
+    /// `x_cg_pro_api_key` param over the public host's `x_cg_demo_api_key`.
+    CoinGecko { key: String, pro: bool },
+    AlphaVantage(String),
+}
+
+impl FromStr for PriceSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (kind, value) = s
+            .split_once(':')
+            .ok_or_else(|| anyhow!("price source must be `<kind>:<value>`"))?;
+
+        match &kind.to_lowercase()[..] {
+            "csv" => Ok(PriceSource::Csv(PathBuf::from(value))),
+            "coingecko" => Ok(PriceSource::CoinGecko {
+                key: value.to_string(),
+                pro: false,
+            }),
+            "coingecko-pro" => Ok(PriceSource::CoinGecko {
+                key: value.to_string(),
+                pro: true,
+            }),
+            "alphavantage" => Ok(PriceSource::AlphaVantage(value.to_string())),
+            _ => bail!("Invalid price source kind: {}", kind),
+        }
+    }
+}
+
+impl PriceSource {
+    /// Build the concrete [`PriceProvider`] this source describes, quoting in
+    /// `currency` (the account currency) where the provider supports it.
+    pub fn into_provider(self, currency: Currency) -> Result<Box<dyn PriceProvider>> {
+        match self {
+            PriceSource::Csv(path) => Ok(Box::new(CsvPriceSource::from_path(&path)?)),
+            PriceSource::CoinGecko { key, pro } => {
+                Ok(Box::new(OnlinePriceSource::coingecko(key, pro, currency)))
+            }
+            PriceSource::AlphaVantage(key) => {
+                Ok(Box::new(OnlinePriceSource::alpha_vantage(key, currency)))
+            }
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PriceRow {
+    coin: Coin,
+    date: NaiveDate,
+    close: Decimal,
+}
+
+/// Daily-close prices loaded from a user-supplied CSV with `coin,date,close`
+/// columns. The whole file is read into a map on construction, so lookups are
+/// already cache-warm.
+pub struct CsvPriceSource {
+    prices: HashMap<(Coin, NaiveDate), Decimal>,
+}
+
+impl CsvPriceSource {
+    pub fn from_path(path: &Path) -> Result<Self> {
+        let mut rdr = csv::Reader::from_path(path)?;
+        let mut prices = HashMap::new();
+
+        for result in rdr.deserialize() {
+            let row: PriceRow = result?;
+            prices.insert((row.coin, row.date), row.close);
+        }
+
+        Ok(Self { prices })
+    }
+}
+
+impl PriceProvider for CsvPriceSource {
+    fn price_on(&mut self, coin: &Coin, date: NaiveDate) -> Result<Option<Decimal>> {
+        Ok(self.prices.get(&(coin.clone(), date)).copied())
+    }
+}
+
+/// Which online quote API an [`OnlinePriceSource`] talks to.
+enum Api {
+    /// CoinGecko; `pro` selects the paid host/param, free keys use the demo one.
+    CoinGecko { pro: bool },
+    AlphaVantage,
+}
+
+/// Fetches historical spot prices from an online provider keyed by an API key,
+/// memoizing every `(coin, date)` lookup so repeated timestamps on the same day
+/// only hit the network once.
+pub struct OnlinePriceSource {
+    api: Api,
+    api_key: String,
+    currency: Currency,
+    cache: HashMap<(Coin, NaiveDate), Option<Decimal>>,
+}
+
+impl OnlinePriceSource {
+    pub fn coingecko(api_key: String, pro: bool, currency: Currency) -> Self {
+        Self {
+            api: Api::CoinGecko { pro },
+            api_key,
+            currency,
+            cache: HashMap::new(),
+        }
+    }
+
+    pub fn alpha_vantage(api_key: String, currency: Currency) -> Self {
+        Self {
+            api: Api::AlphaVantage,
+            api_key,
+            currency,
+            cache: HashMap::new(),
+        }
+    }
+
+    fn fetch(&self, coin: &Coin, date: NaiveDate) -> Result<Option<Decimal>> {
+        let url = match self.api {
+            // CoinGecko's history endpoint keys on the coin id, not a ticker.
+            // Pro keys hit the pro-api host with `x_cg_pro_api_key`; free/demo
+            // keys authenticate against the public host via `x_cg_demo_api_key`.
+            Api::CoinGecko { pro } => {
+                let Some(id) = coin.coingecko_id() else {
+                    return Ok(None);
+                };
+                let (host, key_param) = if pro {
+                    ("pro-api.coingecko.com", "x_cg_pro_api_key")
+                } else {
+                    ("api.coingecko.com", "x_cg_demo_api_key")
+                };
+                format!(
+                    "https://{}/api/v3/coins/{}/history?date={}&{}={}",
+                    host,
+                    id,
+                    date.format("%d-%m-%Y"),
+                    key_param,
+                    self.api_key,
+                )
+            }
+            // Alpha Vantage keys on the canonical ticker, not the output symbol.
+            Api::AlphaVantage => {
+                let Some(ticker) = coin.ticker() else {
+                    return Ok(None);
+                };
+                format!(
+                    "https://www.alphavantage.co/query?function=DIGITAL_CURRENCY_DAILY\
+                     &symbol={}&market={}&apikey={}",
+                    ticker,
+                    self.currency.code(),
+                    self.api_key,
+                )
+            }
+        };
+
+        // Surface rate-limit / auth failures instead of letting a 429 body fall
+        // through `parse_close` as a silent `Ok(None)`.
+        let body = reqwest::blocking::get(url)?.error_for_status()?.text()?;
+        parse_close(&self.api, &body, date, self.currency)
+    }
+}
+
+impl PriceProvider for OnlinePriceSource {
+    fn price_on(&mut self, coin: &Coin, date: NaiveDate) -> Result<Option<Decimal>> {
+        if let Some(cached) = self.cache.get(&(coin.clone(), date)) {
+            return Ok(*cached);
+        }
+
+        let price = self.fetch(coin, date)?;
+        self.cache.insert((coin.clone(), date), price);
+        Ok(price)
+    }
+}
+
+/// Pull the close price, denominated in `currency`, out of a provider's JSON
+/// response.
+fn parse_close(
+    api: &Api,
+    body: &str,
+    date: NaiveDate,
+    currency: Currency,
+) -> Result<Option<Decimal>> {
+    let json: serde_json::Value = serde_json::from_str(body)?;
+
+    let value = match api {
+        Api::CoinGecko { .. } => json
+            .get("market_data")
+            .and_then(|m| m.get("current_price"))
+            .and_then(|p| p.get(currency.code().to_lowercase())),
+        Api::AlphaVantage => json
+            .get("Time Series (Digital Currency Daily)")
+            .and_then(|series| series.get(date.format("%Y-%m-%d").to_string()))
+            .and_then(|day| day.get(format!("4a. close ({})", currency.code()))),
+    };
+
+    match value {
+        None => Ok(None),
+        Some(v) => {
+            let raw = v
+                .as_str()
+                .map(str::to_string)
+                .or_else(|| v.as_f64().map(|f| f.to_string()))
+                .ok_or_else(|| anyhow!("unexpected price encoding in response"))?;
+            Ok(Some(Decimal::from_str(&raw)?))
+        }
+    }
+}